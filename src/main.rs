@@ -4,12 +4,28 @@ use pathsearch::find_executable_in_path;
 use std::fs;
 use git2::{Repository, IndexAddOption, FetchOptions, RemoteCallbacks, Remote, PushOptions};
 use git2_credentials::CredentialHandler;
-use eventual::{Timer};
 use std::time::Duration;
 use chrono::{Local, Timelike, Datelike};
 use std::fs::File;
 use std::io::BufReader;
 use rodio::Source;
+use std::path::Path;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use indicatif::ProgressBar;
+use rusqlite::{params, Connection};
+
+#[derive(serde::Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Interval,
+    Watch,
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Interval
+    }
+}
 
 // #[derive(Debug, Deserialize, Clone, Copy)]
 #[derive(serde::Deserialize)]
@@ -17,9 +33,293 @@ pub struct Config {
     interval_minutes: u32,
     repo_path: String,
     branch_name: String,
+    #[serde(default)]
+    mode: Mode,
+    #[serde(default)]
+    ssh_private_key: Option<String>,
+    #[serde(default)]
+    ssh_public_key: Option<String>,
+    #[serde(default)]
+    ssh_passphrase: Option<String>,
+    /// External "askpass" helper: the program is spawned and the first line of
+    /// its stdout is used as the key passphrase, so the daemon can integrate
+    /// with a system secret store instead of keeping it in plaintext TOML.
+    #[serde(default)]
+    ssh_askpass: Option<String>,
+    #[serde(default)]
+    sync_submodules: bool,
+    #[serde(default)]
+    notify: NotifyConfig,
+    #[serde(default = "default_db_path")]
+    db_path: String,
+    #[serde(default)]
+    conflict_strategy: ConflictStrategy,
+}
+
+/// How `pull` reacts when a merge leaves the index with conflicts.
+#[derive(serde::Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictStrategy {
+    /// Clean up the half-merged state and abort (historical behavior).
+    Abort,
+    /// Stash local changes, fast-forward to the remote, then reapply.
+    StashLocal,
+    /// Auto-resolve every conflict by keeping the local side.
+    Ours,
+    /// Auto-resolve every conflict by keeping the remote side.
+    Theirs,
+}
+
+impl Default for ConflictStrategy {
+    fn default() -> Self {
+        ConflictStrategy::Abort
+    }
+}
+
+fn default_db_path() -> String {
+    "git-auto-sync.db".to_owned()
+}
+
+/// Sinks that receive sync events. Every field is optional so an empty
+/// `[notify]` section (or none at all) keeps the historical WAV-only default
+/// once `sound` points at `assets/error.wav`.
+#[derive(serde::Deserialize)]
+pub struct NotifyConfig {
+    /// Path to a WAV played on the `error` event (the original behavior).
+    #[serde(default = "default_sound")]
+    sound: Option<String>,
+    /// URL that receives a POST with a JSON body on every event.
+    #[serde(default)]
+    webhook: Option<String>,
+    #[serde(default)]
+    mastodon: Option<MastodonConfig>,
+    /// Human-readable template with `{repo}`/`{branch}`/`{oid}` placeholders.
+    #[serde(default)]
+    status_template: Option<String>,
+}
+
+/// Preserve the historical beep when no `sound` (or `[notify]` section) is set.
+fn default_sound() -> Option<String> {
+    Some("assets/error.wav".to_owned())
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        NotifyConfig {
+            sound: default_sound(),
+            webhook: None,
+            mastodon: None,
+            status_template: None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct MastodonConfig {
+    base_url: String,
+    access_token: String,
+}
+
+/// The lifecycle points a sync can report.
+#[derive(Clone, Copy)]
+pub enum Event {
+    CommitCreated,
+    Merged,
+    Pushed,
+    Conflict,
+    Error,
 }
 
-fn commit(repo: &Repository) -> Result<(), git2::Error> {
+impl Event {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Event::CommitCreated => "commit_created",
+            Event::Merged => "merged",
+            Event::Pushed => "pushed",
+            Event::Conflict => "conflict",
+            Event::Error => "error",
+        }
+    }
+}
+
+/// Render the status template for the human-facing sinks (webhook text,
+/// Mastodon). Falls back to a terse default when no template is configured.
+fn render_status(config: &Config, branch_name: &str, oid: Option<&str>) -> String {
+    let oid = oid.unwrap_or("");
+    match &config.notify.status_template {
+        Some(template) => template
+            .replace("{repo}", &config.repo_path)
+            .replace("{branch}", branch_name)
+            .replace("{oid}", oid),
+        None => format!("git-auto-sync: {} @ {}", config.repo_path, branch_name),
+    }
+}
+
+fn notify(config: &Config, branch_name: &str, event: Event, oid: Option<&str>, error: Option<&str>) {
+    let timestamp = Local::now().to_rfc3339();
+
+    if let Some(path) = &config.notify.sound {
+        if let Event::Error = event {
+            play_sound(path);
+        }
+    }
+
+    if let Some(url) = &config.notify.webhook {
+        let url = url.clone();
+        let body = serde_json::json!({
+            "event": event.as_str(),
+            "repo": config.repo_path,
+            "branch": branch_name,
+            "commit_oid": oid,
+            "timestamp": timestamp,
+            "error": error,
+            "status": render_status(config, branch_name, oid),
+        });
+        // Don't block the tokio worker on the network round-trip; hand the
+        // request off to the blocking pool with the shared client.
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = http_client().post(&url).json(&body).send() {
+                println!("webhook notify failed: {}", e);
+            }
+        });
+    }
+
+    if let Some(mastodon) = &config.notify.mastodon {
+        let status = render_status(config, branch_name, oid);
+        let endpoint = format!("{}/api/v1/statuses", mastodon.base_url.trim_end_matches('/'));
+        let token = mastodon.access_token.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = http_client()
+                .post(&endpoint)
+                .bearer_auth(&token)
+                .form(&[("status", status.as_str())])
+                .send();
+            if let Err(e) = result {
+                println!("mastodon notify failed: {}", e);
+            }
+        });
+    }
+}
+
+/// A process-wide reqwest client so each notification reuses the connection
+/// pool instead of spinning up a fresh client (and thread pool) per event.
+fn http_client() -> &'static reqwest::blocking::Client {
+    static HTTP_CLIENT: std::sync::OnceLock<reqwest::blocking::Client> = std::sync::OnceLock::new();
+    HTTP_CLIENT.get_or_init(reqwest::blocking::Client::new)
+}
+
+/// Open the history database, creating the schema on first run.
+fn open_history(db_path: &str) -> Result<Connection, rusqlite::Error> {
+    let connection = Connection::open(db_path)?;
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            ts INTEGER NOT NULL,
+            timestamp TEXT NOT NULL,
+            action TEXT NOT NULL,
+            oid TEXT,
+            deltas INTEGER NOT NULL,
+            conflict INTEGER NOT NULL,
+            error TEXT
+        )",
+        [],
+    )?;
+    Ok(connection)
+}
+
+/// Record one operation of a sync cycle. Best-effort: a logging failure must
+/// not abort the sync itself, so errors are printed rather than propagated.
+fn log_history(config: &Config, action: &str, oid: Option<&str>, deltas: usize, conflict: bool, error: Option<&str>) {
+    let now = Local::now();
+    let result = open_history(&config.db_path).and_then(|connection| {
+        connection.execute(
+            "INSERT INTO history (ts, timestamp, action, oid, deltas, conflict, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![now.timestamp(), now.to_rfc3339(), action, oid, deltas as i64, conflict as i64, error],
+        )
+    });
+    if let Err(e) = result {
+        println!("history log failed: {}", e);
+    }
+}
+
+/// Print recent history rows, optionally limited to the last `since` seconds.
+fn show_history(config: &Config, since: Option<i64>) -> Result<(), rusqlite::Error> {
+    let connection = open_history(&config.db_path)?;
+    let cutoff = since.map(|seconds| Local::now().timestamp() - seconds).unwrap_or(0);
+
+    let mut statement = connection.prepare(
+        "SELECT timestamp, action, oid, deltas, conflict, error
+         FROM history WHERE ts >= ?1 ORDER BY ts ASC",
+    )?;
+    let rows = statement.query_map(params![cutoff], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, Option<String>>(5)?,
+        ))
+    })?;
+
+    for row in rows {
+        let (timestamp, action, oid, deltas, conflict, error) = row?;
+        println!("{} {:<7} oid={} deltas={}{}{}",
+                 timestamp,
+                 action,
+                 oid.unwrap_or_else(|| "-".to_owned()),
+                 deltas,
+                 if conflict != 0 { " CONFLICT" } else { "" },
+                 error.map(|e| format!(" error={}", e)).unwrap_or_default());
+    }
+
+    Ok(())
+}
+
+/// Parse a duration like `30m`, `2h`, `1d`, `45s` into seconds.
+fn parse_duration(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (value, unit) = text.split_at(text.len().saturating_sub(1));
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return None,
+    };
+    value.parse::<i64>().ok().map(|n| n * multiplier)
+}
+
+/// Play a WAV file through the default output device (best-effort).
+fn play_sound(path: &str) {
+    if let Ok(file) = File::open(path) {
+        if let Some(device) = rodio::default_output_device() {
+            if let Ok(source) = rodio::Decoder::new(BufReader::new(file)) {
+                rodio::play_raw(&device, source.convert_samples());
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Resolve the SSH key passphrase, preferring the askpass helper over the
+    /// inline value so secrets need not live in the config file.
+    fn resolve_passphrase(&self) -> Option<String> {
+        if let Some(program) = &self.ssh_askpass {
+            if let Ok(output) = std::process::Command::new(program).output() {
+                if let Ok(text) = String::from_utf8(output.stdout) {
+                    if let Some(line) = text.lines().next() {
+                        return Some(line.to_owned());
+                    }
+                }
+            }
+        }
+        self.ssh_passphrase.clone()
+    }
+}
+
+fn commit(repo: &Repository, branch_name: &str, config: &Config) -> Result<(), git2::Error> {
     println!("starting commit...");
 
     let head = repo.head()?;
@@ -44,18 +344,85 @@ fn commit(repo: &Repository) -> Result<(), git2::Error> {
 
     index.write()?;
 
+    notify(config, branch_name, Event::CommitCreated, Some(&commit_oid.to_string()), None);
+    log_history(config, "commit", Some(&commit_oid.to_string()), diff.deltas().count(), false, None);
+
     Ok(())
 }
 
-fn get_remote(repo: &Repository) -> Result<(Remote, RemoteCallbacks), git2::Error> {
+fn get_remote<'a>(repo: &'a Repository, config: &Config) -> Result<(Remote<'a>, RemoteCallbacks<'static>), git2::Error> {
     let remote = repo.find_remote("origin")?;
 
     let mut remote_callbacks = RemoteCallbacks::new();
     let git_config = git2::Config::open_default()?;
     let mut credential_handler = CredentialHandler::new(git_config);
-    remote_callbacks.credentials(move |url, username, allowed|
+
+    let ssh_private_key = config.ssh_private_key.clone();
+    let ssh_public_key = config.ssh_public_key.clone();
+    let ssh_passphrase = config.resolve_passphrase();
+    // Offer the configured key at most once; libgit2 re-invokes this callback
+    // on auth failure, so returning the same credential forever would loop.
+    let mut key_offered = false;
+    remote_callbacks.credentials(move |url, username, allowed| {
+        // Prefer an explicitly configured SSH key (optionally passphrase-
+        // protected), then a running ssh-agent, and finally the default
+        // credential handler for https/other schemes.
+        if allowed.contains(git2::CredentialType::SSH_KEY) {
+            let user = username.unwrap_or("git");
+            if !key_offered {
+                if let Some(private_key) = &ssh_private_key {
+                    let public_key = ssh_public_key.as_deref().map(Path::new);
+                    let cred = git2::Cred::ssh_key(
+                        user,
+                        public_key,
+                        Path::new(private_key),
+                        ssh_passphrase.as_deref(),
+                    );
+                    if cred.is_ok() {
+                        key_offered = true;
+                        return cred;
+                    }
+                }
+            }
+            if let Ok(cred) = git2::Cred::ssh_key_from_agent(user) {
+                return Ok(cred);
+            }
+        }
         credential_handler.try_next_credential(url, username, allowed)
-    );
+    });
+
+    // Render transfer progress so large syncs over slow links are observable
+    // instead of appearing hung. Fetch and push each get their own bar whose
+    // length is the total object count.
+    let fetch_bar = ProgressBar::new(0);
+    remote_callbacks.transfer_progress(move |progress| {
+        let total = progress.total_objects() as u64;
+        if fetch_bar.length() != total {
+            fetch_bar.set_length(total);
+        }
+        let done = if progress.received_objects() == progress.total_objects() {
+            progress.indexed_objects()
+        } else {
+            progress.received_objects()
+        };
+        fetch_bar.set_position(done as u64);
+        if progress.received_objects() == progress.total_objects() {
+            fetch_bar.finish();
+        }
+        true
+    });
+
+    let push_bar = ProgressBar::new(0);
+    remote_callbacks.push_transfer_progress(move |current, total, _bytes| {
+        if push_bar.length() != total as u64 {
+            push_bar.set_length(total as u64);
+        }
+        push_bar.set_position(current as u64);
+        if total > 0 && current == total {
+            push_bar.finish();
+        }
+    });
+
     remote_callbacks.push_update_reference(move |name, status| {
         println!("ref pushed. name: {}; status: {:?}", name, status);
         Ok(())
@@ -63,10 +430,10 @@ fn get_remote(repo: &Repository) -> Result<(Remote, RemoteCallbacks), git2::Erro
     Ok((remote, remote_callbacks))
 }
 
-fn pull(repo: &Repository, branch_name: &str) -> Result<(), git2::Error> {
+fn pull(repo: &Repository, branch_name: &str, config: &Config) -> Result<(), git2::Error> {
     println!("starting pull...");
 
-    let (mut remote, remote_callbacks) = get_remote(repo)?;
+    let (mut remote, remote_callbacks) = get_remote(repo, config)?;
 
     let mut fetch_options = FetchOptions::new();
     fetch_options
@@ -75,6 +442,15 @@ fn pull(repo: &Repository, branch_name: &str) -> Result<(), git2::Error> {
 
     remote.fetch(&[branch_name], Some(&mut fetch_options), None)?;
 
+    let stats = remote.stats();
+    println!("received {}/{} objects ({} bytes)",
+             stats.received_objects(),
+             stats.total_objects(),
+             stats.received_bytes());
+    if stats.local_objects() > 0 {
+        println!("reused {} local objects from a thin pack", stats.local_objects());
+    }
+
     let remote_ref_name = "refs/remotes/origin/".to_owned() + branch_name;
     let remote_ref = repo.find_reference(remote_ref_name.as_str())?;
     let remote_commit_ann = repo.reference_to_annotated_commit(&remote_ref)?;
@@ -87,6 +463,13 @@ fn pull(repo: &Repository, branch_name: &str) -> Result<(), git2::Error> {
     }
 
     if analysis.is_fast_forward() || analysis.is_normal() {
+        // `stash-local` must engage before the merge so the stash runs on a
+        // clean tree rather than a conflicted, half-merged one.
+        if config.conflict_strategy == ConflictStrategy::StashLocal {
+            println!("stashing local changes before merge...");
+            return stash_local_sync(repo, branch_name, config, &remote_commit_ann, analysis.is_fast_forward());
+        }
+
         println!("merging...");
 
         let head = repo.head()?;
@@ -107,7 +490,23 @@ fn pull(repo: &Repository, branch_name: &str) -> Result<(), git2::Error> {
                          String::from_utf8(conflict_path)
                              .unwrap_or(String::from("<conflict_invalid_path>")));
             }
-            return Err(git2::Error::from_str("aborting: conflicts found"));
+            notify(config, branch_name, Event::Conflict, None, None);
+
+            match config.conflict_strategy {
+                // StashLocal is handled before the merge above; treat it like
+                // Abort here for safety.
+                ConflictStrategy::Abort | ConflictStrategy::StashLocal => {
+                    // Leave a clean tree rather than a half-merged one.
+                    repo.reset(parent_commit.as_object(), git2::ResetType::Hard, None)?;
+                    repo.cleanup_state()?;
+                    log_history(config, "pull", None, 0, true, Some("conflicts found"));
+                    return Err(git2::Error::from_str("aborting: conflicts found"));
+                }
+                ConflictStrategy::Ours | ConflictStrategy::Theirs => {
+                    resolve_conflicts(repo, &mut index, config.conflict_strategy)?;
+                    log_history(config, "pull", None, 0, true, Some("auto-resolved"));
+                }
+            }
         }
 
         let diff = repo.diff_tree_to_index(Some(&parent_commit.tree()?), Some(&index), None)?;
@@ -135,16 +534,139 @@ fn pull(repo: &Repository, branch_name: &str) -> Result<(), git2::Error> {
 
         repo.cleanup_state()?;
 
+        notify(config, branch_name, Event::Merged, Some(&commit_oid.to_string()), None);
+        log_history(config, "pull", Some(&commit_oid.to_string()), diff.deltas().count(), false, None);
+
         return Ok(());
     }
 
     return Err(git2::Error::from_str("Unknown merge analysis result"));
 }
 
-fn push(repo: &Repository, branch_name: &str) -> Result<(), git2::Error> {
+/// Auto-resolve every conflicting index entry by staging the chosen side's
+/// blob, then clear the conflict. Reports each file and the side kept.
+fn resolve_conflicts(repo: &Repository, index: &mut git2::Index, strategy: ConflictStrategy) -> Result<(), git2::Error> {
+    let conflicts: Vec<git2::IndexConflict> = index.conflicts()?.collect::<Result<_, _>>()?;
+
+    for conflict in conflicts {
+        let chosen = match strategy {
+            ConflictStrategy::Ours => conflict.our,
+            ConflictStrategy::Theirs => conflict.their,
+            _ => None,
+        };
+
+        let path = conflict
+            .ancestor.as_ref()
+            .or(conflict.our.as_ref())
+            .or(conflict.their.as_ref())
+            .map(|entry| entry.path.clone())
+            .unwrap_or_default();
+
+        index.remove_path(Path::new(&String::from_utf8_lossy(&path).into_owned()))?;
+
+        match chosen {
+            Some(mut entry) => {
+                // Stage 0 flags mark the entry as resolved.
+                entry.flags = 0;
+                entry.flags_extended = 0;
+                index.add(&entry)?;
+                println!("resolved {} ({})",
+                         String::from_utf8_lossy(&entry.path),
+                         if let ConflictStrategy::Ours = strategy { "ours" } else { "theirs" });
+            }
+            None => {
+                // The chosen side deleted the file; the removal above is the resolution.
+                println!("resolved {} (deleted)", String::from_utf8_lossy(&path));
+            }
+        }
+    }
+
+    index.write()?;
+
+    // `repo.merge` already wrote conflict markers into the work tree; force the
+    // work tree back to the resolved index so the next cycle doesn't re-commit
+    // the marker text.
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.force();
+    repo.checkout_index(Some(index), Some(&mut checkout))?;
+
+    Ok(())
+}
+
+/// Stash local changes, integrate the remote, then reapply and re-commit.
+/// Runs *before* any merge is attempted, so the stash operates on a clean,
+/// non-conflicted state. A fast-forward advances the ref; a diverged history
+/// is merged so un-pushed local commits are preserved rather than orphaned.
+/// Residual conflicts after reapplying are surfaced.
+fn stash_local_sync(repo: &Repository, branch_name: &str, config: &Config, remote_commit_ann: &git2::AnnotatedCommit, is_fast_forward: bool) -> Result<(), git2::Error> {
+    // The stash API needs a mutable handle; reopen the same repository.
+    let mut repo = Repository::open(repo.path())?;
+    let signature = repo.signature()?;
+
+    // `run` commits before pulling, so the tree is often already clean and
+    // there is nothing to stash; treat that as a no-op rather than an error.
+    let stashed = match repo.stash_save2(&signature, "git-auto-sync stash-local", None) {
+        Ok(oid) => {
+            println!("stashed local changes: {}", oid);
+            true
+        }
+        Err(e) if e.code() == git2::ErrorCode::NotFound => {
+            println!("nothing to stash; syncing remote directly");
+            false
+        }
+        Err(e) => return Err(e),
+    };
+
+    let remote_commit = repo.find_commit(remote_commit_ann.id())?;
+    let head_commit = repo.head()?.peel_to_commit()?;
+
+    let base_oid = if is_fast_forward {
+        // Pure fast-forward: advancing the ref drops no local commits.
+        let head_ref_name = "refs/heads/".to_owned() + branch_name;
+        repo.reference(&head_ref_name, remote_commit.id(), true, "fast-forward")?;
+        repo.set_head(&head_ref_name)?;
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+        remote_commit.id()
+    } else {
+        // Diverged history: merge so local commits survive as a parent.
+        repo.merge(&[remote_commit_ann], None, None)?;
+        let mut index = repo.index()?;
+        if index.has_conflicts() {
+            repo.cleanup_state()?;
+            log_history(config, "pull", None, 0, true, Some("conflicts merging diverged history"));
+            return Err(git2::Error::from_str("aborting: conflicts found"));
+        }
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let merge_oid = repo.commit(
+            repo.head()?.name(),
+            &signature,
+            &signature,
+            "merge",
+            &tree,
+            &[&head_commit, &remote_commit])?;
+        repo.cleanup_state()?;
+        merge_oid
+    };
+
+    // Reapply the stashed work on top of the new base and re-commit it.
+    if stashed {
+        repo.stash_pop(0, None)?;
+        if repo.index()?.has_conflicts() {
+            log_history(config, "pull", None, 0, true, Some("residual conflicts after stash reapply"));
+            return Err(git2::Error::from_str("residual conflicts after stash reapply"));
+        }
+        commit(&repo, branch_name, config)?;
+    }
+
+    log_history(config, "pull", Some(&base_oid.to_string()), 0, false, None);
+    notify(config, branch_name, Event::Merged, Some(&base_oid.to_string()), None);
+    Ok(())
+}
+
+fn push(repo: &Repository, branch_name: &str, config: &Config) -> Result<(), git2::Error> {
     println!("starting push...");
 
-    let (mut remote, remote_callbacks) = get_remote(repo)?;
+    let (mut remote, remote_callbacks) = get_remote(repo, config)?;
 
     let mut push_options = PushOptions::new();
     push_options.remote_callbacks(remote_callbacks);
@@ -152,10 +674,77 @@ fn push(repo: &Repository, branch_name: &str) -> Result<(), git2::Error> {
     let head_ref_name = "refs/heads/".to_owned() + branch_name;
     remote.push(&[head_ref_name], Some(&mut push_options))?;
 
+    notify(config, branch_name, Event::Pushed, None, None);
+    log_history(config, "push", None, 0, false, None);
+
     return Ok(());
 }
 
-async fn run(repo: &Repository, branch_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+fn fast_forward(repo: &Repository, branch_name: &str, config: &Config) -> Result<bool, git2::Error> {
+    let (mut remote, remote_callbacks) = get_remote(repo, config)?;
+
+    let mut fetch_options = FetchOptions::new();
+    fetch_options
+        .remote_callbacks(remote_callbacks)
+        .update_fetchhead(true);
+
+    remote.fetch(&[branch_name], Some(&mut fetch_options), None)?;
+
+    let remote_ref_name = "refs/remotes/origin/".to_owned() + branch_name;
+    let remote_ref = repo.find_reference(remote_ref_name.as_str())?;
+    let remote_commit_ann = repo.reference_to_annotated_commit(&remote_ref)?;
+
+    let (analysis, _) = repo.merge_analysis(&[&remote_commit_ann])?;
+
+    if analysis.is_up_to_date() {
+        return Ok(false);
+    }
+
+    if analysis.is_fast_forward() {
+        let remote_commit = remote_ref.peel_to_commit()?;
+        // Move the checkout to the fetched commit directly, leaving the
+        // submodule detached as git expects rather than attaching a branch.
+        repo.set_head_detached(remote_commit.id())?;
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+        return Ok(true);
+    }
+
+    Err(git2::Error::from_str("submodule diverged; cannot fast-forward"))
+}
+
+fn sync_submodules(repo: &Repository, branch_name: &str, config: &Config) -> Result<(), git2::Error> {
+    let mut index = repo.index()?;
+
+    for mut submodule in repo.submodules()? {
+        let path = submodule.path().to_owned();
+        let name = path.to_string_lossy().into_owned();
+
+        let sub_repo = match submodule.open() {
+            Ok(sub_repo) => sub_repo,
+            Err(_) => {
+                println!("submodule {}: uninitialized, skipping", name);
+                continue;
+            }
+        };
+
+        // A submodule may track its own branch; fall back to the superproject's.
+        let sub_branch = submodule.branch().map(|b| b.to_owned()).unwrap_or_else(|| branch_name.to_owned());
+
+        match fast_forward(&sub_repo, &sub_branch, config) {
+            Ok(true) => {
+                println!("submodule {}: fast-forwarded", name);
+                index.add_path(&path)?;
+            }
+            Ok(false) => println!("submodule {}: up to date", name),
+            Err(e) => println!("submodule {}: {}", name, e),
+        }
+    }
+
+    index.write()?;
+    Ok(())
+}
+
+async fn run(repo: &Repository, branch_name: &str, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     let now = Local::now();
     println!("{:02}/{:02}/{:04} {:02}:{:02}:{:02}",
              now.day(),
@@ -165,9 +754,14 @@ async fn run(repo: &Repository, branch_name: &str) -> Result<(), Box<dyn std::er
              now.minute(),
              now.second());
 
-    commit(&repo)?;
-    pull(&repo, branch_name)?;
-    push(&repo, branch_name)?;
+    commit(&repo, branch_name, config)?;
+    pull(&repo, branch_name, config)?;
+    if config.sync_submodules {
+        sync_submodules(&repo, branch_name, config)?;
+        // Capture any freshly staged submodule pointers in the superproject.
+        commit(&repo, branch_name, config)?;
+    }
+    push(&repo, branch_name, config)?;
     Ok(())
 }
 
@@ -177,28 +771,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config_bytes = fs::read(config_path).expect("Error reading config file");
     let config: Config = toml::from_slice(&config_bytes).expect("Error parsing config file");
 
-    let repo = Repository::open(config.repo_path).expect("Error opening repository");
+    // `git-auto-sync history [--since <duration>]` reads back recent cycles
+    // instead of starting the daemon.
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("history") {
+        let mut since = None;
+        while let Some(arg) = args.next() {
+            if arg == "--since" {
+                since = args.next().and_then(|value| parse_duration(&value));
+            }
+        }
+        show_history(&config, since).expect("Error reading history");
+        return Ok(());
+    }
+
+    let repo_path = config.repo_path.clone();
+    let repo = Repository::open(&repo_path).expect("Error opening repository");
     let branch_name = config.branch_name.as_str();
 
     let interval_ms = config.interval_minutes * 1000 * 60;
 
     let handled_run = || async {
         let run = async {
-            run(&repo, branch_name).await.unwrap_or_else(|e| {
+            run(&repo, branch_name, &config).await.unwrap_or_else(|e| {
                 println!("run error: {}", e);
-
-                if let Ok(mut dir) = std::env::current_dir() {
-                    if dir.ends_with(r"\debug") {
-                        dir.push(r"\..\..");
-                    }
-
-                    let wav_path = dir.join("assets").join("error.wav");
-                    let file = File::open(wav_path).unwrap();
-
-                    let device = rodio::default_output_device().unwrap();
-                    let source = rodio::Decoder::new(BufReader::new(file)).unwrap();
-                    rodio::play_raw(&device, source.convert_samples());
-                }
+                notify(&config, branch_name, Event::Error, None, Some(&e.to_string()));
             })
         };
 
@@ -212,9 +809,102 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     handled_run().await;
-    for _ in Timer::new().interval_ms(interval_ms).iter() {
-        handled_run().await;
+
+    // Both modes share the same inputs/event architecture: an out-of-band
+    // SIGUSR1 forces an immediate sync, and SIGINT/SIGTERM trigger a clean
+    // shutdown. The sync runs after `select!` resolves, so a signal is only
+    // observed between cycles and never cancels an in-flight `run`.
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut sigint = signal(SignalKind::interrupt()).expect("Error installing SIGINT handler");
+    let mut sigterm = signal(SignalKind::terminate()).expect("Error installing SIGTERM handler");
+    let mut sigusr1 = signal(SignalKind::user_defined1()).expect("Error installing SIGUSR1 handler");
+
+    match config.mode {
+        Mode::Watch => {
+            // Debounce a burst of filesystem events into a single sync and keep
+            // the timed pull as a periodic fallback so remote-originated changes
+            // are still fetched even while the working tree is quiet.
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+            let mut watcher: RecommendedWatcher =
+                Watcher::new_immediate(move |res| {
+                    if let Ok(event) = res {
+                        let _ = tx.send(event);
+                    }
+                }).expect("Error creating watcher");
+            watcher
+                .watch(Path::new(&repo_path), RecursiveMode::Recursive)
+                .expect("Error watching repository");
+
+            let debounce = Duration::from_secs(2);
+            let fallback = Duration::from_millis(interval_ms as u64);
+            loop {
+                tokio::select! {
+                    maybe = rx.recv() => {
+                        // First event of a burst; keep draining until the tree
+                        // has been quiet for the debounce window, then sync.
+                        let mut pending = match maybe {
+                            Some(event) => is_relevant(&event),
+                            None => break,
+                        };
+                        loop {
+                            match tokio::time::timeout(debounce, rx.recv()).await {
+                                Ok(Some(event)) => pending |= is_relevant(&event),
+                                _ => break,
+                            }
+                        }
+                        if pending {
+                            handled_run().await;
+                        }
+                    }
+                    _ = tokio::time::sleep(fallback) => {
+                        // Periodic fallback: pull in case the remote moved.
+                        handled_run().await;
+                    }
+                    _ = sigusr1.recv() => {
+                        println!("SIGUSR1: forcing immediate sync");
+                        handled_run().await;
+                    }
+                    _ = sigint.recv() => break,
+                    _ = sigterm.recv() => break,
+                }
+            }
+            println!("received termination signal; shutting down");
+        }
+        Mode::Interval => {
+            let mut ticker = tokio::time::interval(Duration::from_millis(interval_ms as u64));
+            ticker.tick().await; // consume the immediate first tick (already synced above)
+
+            loop {
+                let terminate = tokio::select! {
+                    _ = ticker.tick() => { handled_run().await; false }
+                    _ = sigusr1.recv() => {
+                        println!("SIGUSR1: forcing immediate sync");
+                        handled_run().await;
+                        false
+                    }
+                    _ = sigint.recv() => true,
+                    _ = sigterm.recv() => true,
+                };
+
+                if terminate {
+                    println!("received termination signal; shutting down");
+                    break;
+                }
+            }
+        }
+    }
+
+    // If a merge was left pending, clear its state so we exit on solid ground.
+    if repo.state() != git2::RepositoryState::Clean {
+        let _ = repo.cleanup_state();
     }
 
     Ok(())
 }
+
+/// Ignore events inside `.git/` so our own commits don't retrigger a sync.
+fn is_relevant(event: &notify::Event) -> bool {
+    event.paths.iter().any(|path| {
+        !path.components().any(|component| component.as_os_str() == ".git")
+    })
+}